@@ -1,11 +1,13 @@
 use serde::Deserialize;
 use std::fs;
-use rexif::ExifTag;
-use rexif::parse_file;
+use exif::{In, Reader, Tag, Value};
+use std::io::BufReader;
 use glob::glob;
-use std::path::Path;
-use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::collections::{BTreeMap, HashMap};
+use rayon::prelude::*;
 use std::hash::{Hash, Hasher};
+use chrono::{Datelike, NaiveDateTime};
 
 #[derive(Debug, Copy, Clone)]
 struct F64(pub f64);
@@ -29,6 +31,83 @@ struct MetaData {
     f_stop: F64,
     exposure: String,
     iso: u32,
+    lens: Option<String>,
+    focal_length: Option<F64>,
+}
+
+impl MetaData {
+    // Project onto the configured grouping fields, canonicalising everything
+    // not listed in `group_by` so photos that differ only in an excluded field
+    // collapse into the same bucket.
+    fn project(&self, group_by: &[String]) -> MetaData {
+        let keep = |field: &str| group_by.iter().any(|g| g == field);
+        MetaData {
+            f_stop: if keep("f_stop") { self.f_stop } else { F64(0.0) },
+            exposure: if keep("exposure") { self.exposure.clone() } else { String::new() },
+            iso: if keep("iso") { self.iso } else { 0 },
+            lens: if keep("lens") { self.lens.clone() } else { None },
+            focal_length: if keep("focal_length") { self.focal_length } else { None },
+        }
+    }
+
+    // Render one field as a CSV cell; absent optional fields become empty.
+    fn field_csv(&self, field: &str) -> String {
+        match field {
+            "f_stop" => self.f_stop.0.to_string(),
+            "exposure" => self.exposure.clone(),
+            "iso" => self.iso.to_string(),
+            "lens" => self.lens.clone().unwrap_or_default(),
+            "focal_length" => self.focal_length.map(|f| f.0.to_string()).unwrap_or_default(),
+            _ => String::new(),
+        }
+    }
+
+    // Render one field as a JSON value; absent optional fields become `null`.
+    fn field_json(&self, field: &str) -> serde_json::Value {
+        match field {
+            "f_stop" => serde_json::json!(self.f_stop.0),
+            "exposure" => serde_json::json!(self.exposure),
+            "iso" => serde_json::json!(self.iso),
+            "lens" => serde_json::json!(self.lens),
+            "focal_length" => serde_json::json!(self.focal_length.map(|f| f.0)),
+            _ => serde_json::Value::Null,
+        }
+    }
+
+    // Build the grouping key from the configured fields. Fields not listed in
+    // `group_by` are excluded from the key; optional fields (lens, focal
+    // length) that are absent are bucketed as "unknown".
+    fn grouping_key(&self, group_by: &[String]) -> String {
+        group_by
+            .iter()
+            .map(|field| match field.as_str() {
+                "f_stop" => format!("f-stop: f/{}", self.f_stop.0),
+                "exposure" => format!("exposure: {}", self.exposure),
+                "iso" => format!("ISO: {}", self.iso),
+                "lens" => format!(
+                    "lens: {}",
+                    self.lens.as_deref().unwrap_or("unknown")
+                ),
+                "focal_length" => format!(
+                    "focal length: {}",
+                    self.focal_length
+                        .map(|f| format!("{}mm", f.0))
+                        .unwrap_or_else(|| "unknown".to_string())
+                ),
+                other => format!("{}: ?", other),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+// Default grouping fields, matching the original exposure-triplet behaviour.
+fn default_group_by() -> Vec<String> {
+    vec!["f_stop".to_string(), "exposure".to_string(), "iso".to_string()]
+}
+
+fn default_output_format() -> String {
+    "text".to_string()
 }
 
 const CONFIG_FILE_PATH: &str = "config.yaml"; // or "config.json"
@@ -36,6 +115,18 @@ const CONFIG_FILE_PATH: &str = "config.yaml"; // or "config.json"
 #[derive(Deserialize)]
 struct Config {
     filepath: String,
+    #[serde(default)]
+    group_by_date: bool,
+    #[serde(default)]
+    use_exiftool_fallback: bool,
+    #[serde(default = "default_group_by")]
+    group_by: Vec<String>,
+    #[serde(default = "default_output_format")]
+    output_format: String,
+    // Number of worker threads for the parallel scan; 0 leaves the choice to
+    // rayon (one thread per logical core).
+    #[serde(default)]
+    num_threads: usize,
 }
 
 // Function to load the config file
@@ -90,72 +181,351 @@ fn main() {
     // Use the pattern from the config file
     let pattern = &config.filepath;
 
-    let mut metadata_map: HashMap<MetaData, u32> = HashMap::new();
+    // Collect the glob matches up front so the expensive EXIF parse can run
+    // across a rayon parallel iterator.
+    let paths: Vec<PathBuf> = glob(pattern)
+        .expect("Failed to read glob pattern")
+        .filter_map(|entry| match entry {
+            Ok(path) => Some(path),
+            Err(e) => {
+                eprintln!("{:?}", e);
+                None
+            }
+        })
+        .collect();
+
+    // A bounded pool keeps the thread count configurable; 0 falls back to the
+    // rayon default of one thread per logical core.
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(config.num_threads)
+        .build()
+        .expect("Failed to build thread pool");
+
+    // Parse each file in parallel, preserving input order so the serial merge
+    // below produces counts identical to a single-threaded pass.
+    let scanned: Vec<(Option<MetaData>, Option<NaiveDateTime>)> = pool.install(|| {
+        paths
+            .par_iter()
+            .map(|path| {
+                // Open and parse the EXIF container once per file; both the
+                // exposure-triplet extraction and the capture date read from
+                // it below, so neither has to re-open the file.
+                let exif_container = read_exif_container(path);
+
+                // In date-grouping mode the exposure triplet is never used
+                // (only the capture date is), so skip decoding it entirely
+                // rather than throwing the result away. This matters at
+                // "tens of thousands of photos" scale.
+                let meta_data = if config.group_by_date {
+                    None
+                } else {
+                    let extracted = exif_container
+                        .as_ref()
+                        .and_then(extract_exif_data)
+                        .or_else(|| {
+                            if config.use_exiftool_fallback {
+                                extract_via_exiftool(path)
+                            } else {
+                                None
+                            }
+                        });
+
+                    extracted.map(|(f_stop, shutter_speed, iso, lens, focal_length)| {
+                        MetaData {
+                            f_stop: F64(f_stop),
+                            exposure: to_closest_shutter_speed(shutter_speed).to_string(),
+                            iso,
+                            lens,
+                            focal_length: focal_length.map(F64),
+                        }
+                        .project(&config.group_by)
+                    })
+                };
 
-    // Iterate over each file matching the pattern
-    for entry in glob(pattern).expect("Failed to read glob pattern") {
-        match entry {
-            Ok(path) => {
-                if let Some((f_stop, shutter_speed, iso)) = extract_exif_data(&path) {
-                    let meta_data = MetaData {
-                        f_stop: F64(f_stop),
-                        exposure: to_closest_shutter_speed(shutter_speed).to_string(),
-                        iso,
-                    };
-
-                    *metadata_map.entry(meta_data).or_insert(0) += 1;
-                }
-            },
-            Err(e) => println!("{:?}", e),
+                // Only resolve the capture date when date grouping is enabled;
+                // otherwise this is wasted work.
+                let date = if config.group_by_date {
+                    let date = capture_date(path, exif_container.as_ref());
+                    if date.is_none() {
+                        eprintln!(
+                            "Warning: no EXIF date or readable mtime for {}, excluding it from the date buckets",
+                            path.display()
+                        );
+                    }
+                    date
+                } else {
+                    None
+                };
+
+                (meta_data, date)
+            })
+            .collect()
+    });
+
+    // Merge the per-file results serially, in input order, for determinism.
+    let mut metadata_map: HashMap<MetaData, u32> = HashMap::new();
+    let mut capture_dates: Vec<NaiveDateTime> = Vec::new();
+    for (meta_data, date) in scanned {
+        if let Some(meta_data) = meta_data {
+            *metadata_map.entry(meta_data).or_insert(0) += 1;
+        }
+        if let Some(date) = date {
+            capture_dates.push(date);
         }
     }
 
-    // Print the grouped results
-    for (data, count) in &metadata_map {
-        println!(
-            "f-stop: f/{}, exposure: {}, ISO: {} -> count: {}",
-            data.f_stop.0, data.exposure, data.iso, count
-        );
+    if config.group_by_date {
+        print_date_buckets(&capture_dates);
+    } else {
+        output_results(&metadata_map, &config);
     }
 }
 
-fn extract_exif_data(path: &Path) -> Option<(f64, f64, u32)> {
-    // Parse the EXIF data from the file path
-    let exif = parse_file(path).ok()?;
+// Quote a CSV cell per RFC 4180 when it contains a comma, quote or newline;
+// embedded quotes are doubled. `lens` comes from EXIF/exiftool and is
+// otherwise unvalidated, so a value like `Tamron 17-28mm, f/2.8` would
+// otherwise shift columns.
+fn csv_quote(value: &str) -> String {
+    if value.contains(',') || value.contains('"') || value.contains('\n') || value.contains('\r') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
 
-    // Extract f-stop
-    let f_stop = exif.entries.iter().find_map(|entry| {
-        if entry.tag == ExifTag::FNumber {
-            entry.value.to_f64(0) // Get the first element
-        } else {
-            None
+fn csv_row(cells: &[String]) -> String {
+    cells.iter().map(|cell| csv_quote(cell)).collect::<Vec<_>>().join(",")
+}
+
+// Render the grouped results in the configured output format. `text` keeps the
+// original human-readable lines, `csv` emits one row per unique bucket, and
+// `json` serialises the buckets as an array of records.
+fn output_results(metadata_map: &HashMap<MetaData, u32>, config: &Config) {
+    match config.output_format.as_str() {
+        "csv" => {
+            // Emit only the grouped fields so the columns match the buckets
+            // rather than reporting canonicalised zeros for excluded fields.
+            let mut header = config.group_by.clone();
+            header.push("count".to_string());
+            println!("{}", csv_row(&header));
+            for (data, count) in metadata_map {
+                let mut cells: Vec<String> =
+                    config.group_by.iter().map(|f| data.field_csv(f)).collect();
+                cells.push(count.to_string());
+                println!("{}", csv_row(&cells));
+            }
         }
+        "json" => {
+            let records: Vec<serde_json::Value> = metadata_map
+                .iter()
+                .map(|(data, count)| {
+                    let mut record = serde_json::Map::new();
+                    for field in &config.group_by {
+                        record.insert(field.clone(), data.field_json(field));
+                    }
+                    record.insert("count".to_string(), serde_json::json!(count));
+                    serde_json::Value::Object(record)
+                })
+                .collect();
+            match serde_json::to_string_pretty(&records) {
+                Ok(json) => println!("{}", json),
+                Err(e) => eprintln!("Warning: could not serialize records to JSON: {}", e),
+            }
+        }
+        _ => {
+            for (data, count) in metadata_map {
+                println!("{} -> count: {}", data.grouping_key(&config.group_by), count);
+            }
+        }
+    }
+}
+
+// Bucket the collected capture dates by year, month and day, printing each
+// bucket in chronological order. A BTreeMap keyed by the formatted date keeps
+// the output sorted without an explicit sort pass.
+fn print_date_buckets(dates: &[NaiveDateTime]) {
+    let mut by_year: BTreeMap<i32, u32> = BTreeMap::new();
+    let mut by_month: BTreeMap<String, u32> = BTreeMap::new();
+    let mut by_day: BTreeMap<String, u32> = BTreeMap::new();
+
+    for date in dates {
+        *by_year.entry(date.year()).or_insert(0) += 1;
+        *by_month.entry(format!("{:04}-{:02}", date.year(), date.month())).or_insert(0) += 1;
+        *by_day
+            .entry(format!("{:04}-{:02}-{:02}", date.year(), date.month(), date.day()))
+            .or_insert(0) += 1;
+    }
+
+    println!("By year:");
+    for (year, count) in &by_year {
+        println!("  {} -> count: {}", year, count);
+    }
+    println!("By month:");
+    for (month, count) in &by_month {
+        println!("  {} -> count: {}", month, count);
+    }
+    println!("By day:");
+    for (day, count) in &by_day {
+        println!("  {} -> count: {}", day, count);
+    }
+}
+
+// Open a file and read its EXIF container. kamadak-exif understands ISO BMFF
+// (HEIC/HEIF) in addition to JPEG/TIFF, so modern iPhone photos parse here.
+// Callers that need more than one piece of EXIF data from a file should read
+// the container once with this and pass it around, rather than re-opening
+// and re-parsing the file for each piece they need.
+fn read_exif_container(path: &Path) -> Option<exif::Exif> {
+    let file = fs::File::open(path).ok()?;
+    Reader::new()
+        .read_from_container(&mut BufReader::new(&file))
+        .ok()
+}
+
+fn extract_exif_data(exif: &exif::Exif) -> Option<(f64, f64, u32, Option<String>, Option<f64>)> {
+    // f-stop and focal length come back as rationals; reduce them to f64.
+    let f_stop = field_to_f64(exif, Tag::FNumber);
+    let shutter_speed = field_to_f64(exif, Tag::ExposureTime);
+    let focal_length = field_to_f64(exif, Tag::FocalLength);
+
+    // ISO is encoded as BYTE/SHORT/LONG depending on the camera; get_uint
+    // normalises across those without the ad-hoc TagValue matching.
+    let iso = exif
+        .get_field(Tag::PhotographicSensitivity, In::PRIMARY)
+        .and_then(|field| field.value.get_uint(0));
+
+    // Lens model is an ASCII string; render it with the reader's display form.
+    let lens = exif.get_field(Tag::LensModel, In::PRIMARY).and_then(|field| {
+        let value = field.display_value().to_string();
+        let value = value.trim().trim_matches('"').to_string();
+        if value.is_empty() { None } else { Some(value) }
     });
 
-    // Extract shutter speed
-    let shutter_speed = exif.entries.iter().find_map(|entry| {
-        if entry.tag == ExifTag::ExposureTime {
-            entry.value.to_f64(0) // Get the first element
-        } else {
+    match (f_stop, shutter_speed, iso) {
+        (Some(f), Some(s), Some(i)) => Some((f, s, i, lens, focal_length)),
+        _ => None,
+    }
+}
+
+// Pull a numeric EXIF value as `f64`, handling the RATIONAL/SRATIONAL encodings
+// used for aperture/exposure/focal length as well as plain integer encodings.
+fn field_to_f64(exif: &exif::Exif, tag: Tag) -> Option<f64> {
+    let field = exif.get_field(tag, In::PRIMARY)?;
+    match field.value {
+        Value::Rational(ref v) => v.first().map(|r| r.to_f64()),
+        Value::SRational(ref v) => v.first().map(|r| r.to_f64()),
+        _ => field.value.get_uint(0).map(|u| u as f64),
+    }
+}
+
+// Recover the exposure triplet by shelling out to the `exiftool` binary when
+// the native reader cannot parse a file (e.g. MOV/MP4 video). Requests JSON
+// output and parses the first record of the resulting array. Degrades to a
+// warning + `None` when the binary is missing or returns nothing usable.
+fn extract_via_exiftool(path: &Path) -> Option<(f64, f64, u32, Option<String>, Option<f64>)> {
+    let output = match std::process::Command::new("exiftool")
+        .args(["-j", "-FNumber", "-ExposureTime", "-ISO", "-LensModel", "-FocalLength"])
+        .arg(path)
+        .output()
+    {
+        Ok(output) => output,
+        Err(e) => {
+            eprintln!("Warning: could not run exiftool for {}: {}", path.display(), e);
+            return None;
+        }
+    };
+
+    let records: Vec<serde_json::Value> =
+        serde_json::from_slice(&output.stdout).unwrap_or_default();
+    let record = match records.first() {
+        Some(record) => record,
+        None => {
+            eprintln!("Warning: exiftool produced no usable EXIF for {}", path.display());
+            return None;
+        }
+    };
+
+    let f_stop = record.get("FNumber").and_then(|v| v.as_f64());
+    let shutter_speed = record.get("ExposureTime").and_then(exiftool_to_seconds);
+    let iso = record.get("ISO").and_then(|v| v.as_u64()).map(|v| v as u32);
+    let lens = record
+        .get("LensModel")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+    let focal_length = record.get("FocalLength").and_then(exiftool_to_mm);
+
+    match (f_stop, shutter_speed, iso) {
+        (Some(f), Some(s), Some(i)) => Some((f, s, i, lens, focal_length)),
+        _ => {
+            eprintln!("Warning: exiftool produced no usable EXIF for {}", path.display());
             None
         }
-    });
+    }
+}
+
+// exiftool reports `ExposureTime` either as a number of seconds or as a
+// fractional string such as `"1/250"`; normalise both into seconds.
+fn exiftool_to_seconds(value: &serde_json::Value) -> Option<f64> {
+    if let Some(seconds) = value.as_f64() {
+        return Some(seconds);
+    }
 
-    // Extract ISO
-    let iso = exif.entries.iter().find_map(|entry| {
-        if entry.tag == ExifTag::ISOSpeedRatings {
-            match &entry.value {
-                rexif::TagValue::U16(values) => values.get(0).cloned().map(|v| v as u32),
-                rexif::TagValue::U32(values) => values.get(0).cloned(),
+    let text = value.as_str()?;
+    match text.split_once('/') {
+        Some((num, den)) => {
+            let num: f64 = num.trim().parse().ok()?;
+            let den: f64 = den.trim().parse().ok()?;
+            if den == 0.0 { None } else { Some(num / den) }
+        }
+        None => text.trim().parse().ok(),
+    }
+}
+
+// exiftool reports `FocalLength` either as a number or as a unit-suffixed
+// string such as `"50.0 mm"`; pull the leading numeric value in millimetres.
+fn exiftool_to_mm(value: &serde_json::Value) -> Option<f64> {
+    if let Some(mm) = value.as_f64() {
+        return Some(mm);
+    }
+
+    let text = value.as_str()?;
+    text.split_whitespace().next()?.parse().ok()
+}
+
+// Determine when a photo was taken, preferring the EXIF DateTimeOriginal (or
+// DateTime) tag from the already-parsed container and falling back to the
+// filesystem modification time when the container is absent or carries no
+// usable EXIF date. Returns `None` only when neither source yields a
+// readable timestamp, so the caller can skip the file with a warning.
+fn capture_date(path: &Path, exif: Option<&exif::Exif>) -> Option<NaiveDateTime> {
+    let exif_date = exif.and_then(|exif| {
+        [Tag::DateTimeOriginal, Tag::DateTime].iter().find_map(|tag| {
+            let field = exif.get_field(*tag, In::PRIMARY)?;
+            match field.value {
+                Value::Ascii(ref v) => v
+                    .first()
+                    .and_then(|bytes| parse_exif_datetime(&String::from_utf8_lossy(bytes))),
                 _ => None,
             }
-        } else {
-            None
-        }
+        })
     });
 
-    match (f_stop, shutter_speed, iso) {
-        (Some(f), Some(s), Some(i)) => Some((f, s, i)),
-        _ => None,
+    if let Some(date) = exif_date {
+        return Some(date);
     }
+
+    // Fall back to the filesystem modification time.
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    let since_epoch = modified.duration_since(std::time::UNIX_EPOCH).ok()?;
+    NaiveDateTime::from_timestamp_opt(since_epoch.as_secs() as i64, 0)
+}
+
+// Parse the EXIF date format `"YYYY:MM:DD HH:MM:SS"` into a `NaiveDateTime`.
+// The date portion uses colons as separators, so swap them for dashes before
+// handing the string to chrono.
+fn parse_exif_datetime(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    let (date, time) = value.split_once(' ')?;
+    let normalized = format!("{} {}", date.replace(':', "-"), time);
+    NaiveDateTime::parse_from_str(&normalized, "%Y-%m-%d %H:%M:%S").ok()
 }